@@ -1,4 +1,6 @@
 use core::str;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::fmt;
 
 /// A DNA sequence have bases and indices. These indices are used to save base position and
@@ -10,7 +12,8 @@ pub struct Sequence {
 
 impl From<&str> for Sequence {
     /// Create a Sequence from a `seq`. Gaps '-' are removed from bases and indices.
-    /// There is no test for valid bases (ACGT or acgt).
+    /// Other characters (IUPAC ambiguity codes, soft-masked lowercase bases, `N`) are kept
+    /// as-is; `DNAMatrix::scan` resolves them at scoring time instead of here.
     fn from(seq: &str) -> Self {
         seq.char_indices()
             .filter(|&(_,x)| !x.eq(&'-'))
@@ -23,9 +26,9 @@ impl From<&str> for Sequence {
 }
 
 /// DNA strand forward or reverse.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Strand {
-    Forward, 
+    Forward,
     Reverse,
 }
 
@@ -39,8 +42,44 @@ impl fmt::Display for Strand {
 }
 
 
+/// How a `DNAMatrix` turns per-column base counts into the per-base weights used by `scan`.
+#[derive(Debug, Clone)]
+pub enum ScoringModel {
+    /// The original behaviour: counts are normalized by the overall max column count (with a
+    /// leftover "gap" probability) and each column is weighted by its conservation.
+    Conservation,
+    /// A true log-odds PSSM, as in rust-bio's `DNAMotif`: counts are converted to frequencies
+    /// with additive `pseudocount` per base, then scored as `log2(freq[base] / background[base])`.
+    LogOdds {
+        pseudocount: f64,
+        background: [f64; 4],
+    },
+}
+
+impl ScoringModel {
+    /// Log-odds scoring with the conventional pseudocount of 0.25 per base and a uniform
+    /// background distribution.
+    pub fn log_odds() -> Self {
+        ScoringModel::LogOdds {
+            pseudocount: 0.25,
+            background: [0.25; 4],
+        }
+    }
+}
+
+/// How `DNAMatrix::scan_with_policy` handles IUPAC ambiguity codes, soft-masked or unknown bases.
+#[derive(Debug, Clone, Copy)]
+pub enum AmbiguousBasePolicy {
+    /// Score an ambiguous/unknown base as the background-weighted average over the bases it
+    /// could represent (e.g. `N` contributes the expected score under the background model).
+    Average,
+    /// Drop any window containing more than `max_ambiguous` ambiguous/unknown positions.
+    SkipWindow { max_ambiguous: usize },
+}
+
 /// Score are assigned to a range, so there is a `start` and an `end` besides the `score` value.
-/// Reverse strand scores have `start` > `end`. 
+/// `scan` keeps its legacy convention of `start` > `end` on the reverse strand; `scan_both`
+/// always reports canonical `start` < `end` coordinates plus an explicit `strand`.
 /// The score length is the same as matrix length
 #[derive(Debug)]
 pub struct Score {
@@ -49,12 +88,20 @@ pub struct Score {
     algn_start: usize,
     algn_end: usize,
     score: f64,
+    raw_score: f64,
+    pvalue: Option<f64>,
+    qvalue: Option<f64>,
+    strand: Strand,
 }
 
 impl fmt::Display for Score {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // write!(f, "{}\t{}\t{:.3}", self.start, self.end, self.score )
-        write!(f, "{}\t{}\t{}\t{}\t{:.3}", self.seq_start, self.seq_end, self.algn_start, self.algn_end, self.score )
+        write!(f, "{}\t{}\t{}\t{}\t{}\t{:.3}", self.seq_start, self.seq_end, self.strand, self.algn_start, self.algn_end, self.score )?;
+        if let Some(q) = self.qvalue {
+            write!(f, "\t{:.3e}", q)?;
+        }
+        Ok(())
     }
 }
 
@@ -68,15 +115,30 @@ pub struct DNAMatrix {
     probs: Vec<Vec<f64>>,
     conservation: Vec<f64>,
     max_score: f64,
+    min_score: f64,
     threshold: f64,
-    pub strand: Strand
+    pub strand: Strand,
+    scoring: ScoringModel,
+    background: [f64; 4],
 }
 
 impl DNAMatrix {
-    pub fn new(name: &str, threshold: f64, counts: &Vec<Vec<f64>>, strand: Strand) -> Self {
-        let probs = match strand {
-            Strand::Forward => Self::calculate_probs(counts),
-            Strand::Reverse => Self::calculate_probs(&Self::comp_rev_counts(counts)),
+    pub fn new(name: &str, threshold: f64, counts: &[Vec<f64>], strand: Strand, scoring: ScoringModel) -> Self {
+        let oriented_counts = match strand {
+            Strand::Forward => counts.to_owned(),
+            Strand::Reverse => Self::comp_rev_counts(counts),
+        };
+
+        let background = match &scoring {
+            ScoringModel::Conservation => [0.25; 4],
+            ScoringModel::LogOdds { background, .. } => *background,
+        };
+
+        let probs = match &scoring {
+            ScoringModel::Conservation => Self::calculate_probs(&oriented_counts),
+            ScoringModel::LogOdds { pseudocount, background } => {
+                Self::calculate_log_odds(&oriented_counts, *pseudocount, background)
+            }
         };
 
         let mut matrix = DNAMatrix {
@@ -85,16 +147,143 @@ impl DNAMatrix {
             probs,
             conservation: vec![],
             max_score: 0.0,
+            min_score: 0.0,
             threshold,
             strand,
+            scoring,
+            background,
         };
-        matrix.calculate_conservation();
-        matrix.calculate_max_score();
+        match matrix.scoring {
+            ScoringModel::Conservation => {
+                matrix.calculate_conservation();
+                matrix.calculate_max_score();
+            }
+            ScoringModel::LogOdds { .. } => matrix.calculate_score_bounds(),
+        }
         matrix
     }
 
-    fn comp_rev_counts(v: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
-        let mut r = v.clone();
+    /// Build a matrix directly from a set of equal-length aligned binding-site sequences,
+    /// tallying A/C/G/T counts per column (mirrors rust-bio's `DNAMotif::from_seqs`).
+    pub fn from_seqs(name: &str, seqs: &[&str], threshold: f64, strand: Strand, scoring: ScoringModel) -> Self {
+        assert!(!seqs.is_empty(), "from_seqs: at least one aligned sequence is required");
+        let length = seqs[0].len();
+        let mut counts = vec![vec![0.0; 4]; length];
+        for seq in seqs {
+            assert_eq!(seq.len(), length, "all sequences must have the same length");
+            for (i, b) in seq.chars().enumerate() {
+                let base = Self::lookup(&b)
+                    .unwrap_or_else(|| panic!("from_seqs: aligned sites must be plain A/C/G/T, found '{}'", b));
+                counts[i][base] += 1.0;
+            }
+        }
+        Self::new(name, threshold, &counts, strand, scoring)
+    }
+
+    /// Parse a JASPAR count matrix, returning the motif name and per-column base counts (in
+    /// A/C/G/T order, one row per alignment column) ready for `DNAMatrix::new`.
+    ///
+    /// Accepts both the bracketed `.jaspar` layout (`">MA0001.1 AGL3"` header, rows like
+    /// `"A  [ 4 19 0 0 0 ]"`) and the raw `.pfm` layout (no header, no brackets, just four rows
+    /// of whitespace-separated counts). Rows that carry a leading base letter are keyed by that
+    /// letter rather than by position, so a `.jaspar` file with its rows reordered still lands in
+    /// the right column; rows with no letter (raw `.pfm`) fall back to the canonical A/C/G/T order.
+    /// Exactly four base rows are required; any other number is an error.
+    pub fn parse_jaspar(contents: &str) -> Result<(String, Vec<Vec<f64>>), String> {
+        let mut lines = contents.lines().peekable();
+        let name = if lines.peek().is_some_and(|l| l.trim_start().starts_with('>')) {
+            lines
+                .next()
+                .unwrap()
+                .trim_start_matches('>')
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or("unknown")
+                .to_string()
+        } else {
+            "unknown".to_string()
+        };
+
+        let mut rows: Vec<(Option<usize>, Vec<f64>)> = vec![];
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let first_char = line.chars().next().unwrap();
+            let base = if first_char.is_alphabetic() {
+                Some(Self::lookup(&first_char).ok_or_else(|| format!("unrecognized base row: {}", line))?)
+            } else {
+                None
+            };
+            let values_part = line.trim_matches(|c: char| c.is_alphabetic() || c.is_whitespace());
+            let values_part = values_part.trim_start_matches('[').trim_end_matches(']');
+            let values: Vec<f64> = values_part
+                .split_whitespace()
+                .map(|v| v.parse::<f64>().map_err(|e| e.to_string()))
+                .collect::<Result<_, _>>()?;
+            rows.push((base, values));
+        }
+        if rows.len() != 4 {
+            return Err(format!("expected 4 base rows, found {}", rows.len()));
+        }
+        let length = rows[0].1.len();
+        let mut counts = vec![vec![0.0; 4]; length];
+        for (i, (base, row)) in rows.iter().enumerate() {
+            let base = base.unwrap_or(i);
+            for (pos, &v) in row.iter().enumerate() {
+                counts[pos][base] = v;
+            }
+        }
+        Ok((name, counts))
+    }
+
+    /// Build a matrix straight from a JASPAR `.pfm`/`.jaspar` count file.
+    pub fn from_jaspar(contents: &str, threshold: f64, strand: Strand, scoring: ScoringModel) -> Result<Self, String> {
+        let (name, counts) = Self::parse_jaspar(contents)?;
+        Ok(Self::new(&name, threshold, &counts, strand, scoring))
+    }
+
+    /// Parse a TRANSFAC matrix table (the `ID` line and the per-position `A C G T` count rows),
+    /// returning the motif name and per-column base counts ready for `DNAMatrix::new`.
+    pub fn parse_transfac(contents: &str) -> Result<(String, Vec<Vec<f64>>), String> {
+        let mut name = String::from("unknown");
+        let mut counts: Vec<Vec<f64>> = vec![];
+        for line in contents.lines() {
+            let line = line.trim();
+            if let Some(rest) = line.strip_prefix("ID") {
+                name = rest.trim().to_string();
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let first = match fields.next() {
+                Some(f) => f,
+                None => continue,
+            };
+            if first.chars().all(|c| c.is_ascii_digit()) {
+                let values: Vec<f64> = fields
+                    .take(4)
+                    .map(|v| v.parse::<f64>().map_err(|e| e.to_string()))
+                    .collect::<Result<_, _>>()?;
+                if values.len() == 4 {
+                    counts.push(values);
+                }
+            }
+        }
+        if counts.is_empty() {
+            return Err("no position rows found in TRANSFAC matrix".to_string());
+        }
+        Ok((name, counts))
+    }
+
+    /// Build a matrix straight from a TRANSFAC matrix table.
+    pub fn from_transfac(contents: &str, threshold: f64, strand: Strand, scoring: ScoringModel) -> Result<Self, String> {
+        let (name, counts) = Self::parse_transfac(contents)?;
+        Ok(Self::new(&name, threshold, &counts, strand, scoring))
+    }
+
+    fn comp_rev_counts(v: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let mut r = v.to_owned();
         r.reverse();
         for b in r.iter_mut() {
             b.reverse();
@@ -102,7 +291,7 @@ impl DNAMatrix {
         r
     }
 
-    fn calculate_probs(counts: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    fn calculate_probs(counts: &[Vec<f64>]) -> Vec<Vec<f64>> {
         let mut probs = vec![];
         let max_count: f64 = counts
             .iter()
@@ -120,6 +309,43 @@ impl DNAMatrix {
         probs
     }
 
+    /// Convert per-column counts to a log-odds matrix: each entry is
+    /// `log2((count + pseudocount) / (total + 4*pseudocount) / background[base])`.
+    fn calculate_log_odds(counts: &[Vec<f64>], pseudocount: f64, background: &[f64; 4]) -> Vec<Vec<f64>> {
+        counts
+            .iter()
+            .map(|position| {
+                if position.len() != 4 {
+                    panic!("Matrix has {} values when 4 is expected.", position.len())
+                }
+                let total: f64 = position.iter().sum::<f64>() + 4.0 * pseudocount;
+                position
+                    .iter()
+                    .zip(background.iter())
+                    .map(|(count, bg)| {
+                        let freq = (count + pseudocount) / total;
+                        (freq / bg).log2()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Track the sum of the worst/best base at each column, so a raw log-odds score can be
+    /// reported as a normalized value between 0 and 1, like the conservation-weighted mode.
+    fn calculate_score_bounds(&mut self) {
+        self.min_score = self
+            .probs
+            .iter()
+            .map(|p| p.iter().cloned().fold(f64::INFINITY, f64::min))
+            .sum();
+        self.max_score = self
+            .probs
+            .iter()
+            .map(|p| p.iter().cloned().fold(f64::NEG_INFINITY, f64::max))
+            .sum();
+    }
+
     fn calculate_conservation(&mut self) {
         for position in self.probs.iter() {
             let sum: f64 = position
@@ -143,34 +369,365 @@ impl DNAMatrix {
             .fold(0.0, |acc, (i, v)| acc + self.conservation[i] * v);
     }
 
+    /// Scan `seq`, scoring ambiguous/unknown bases as the background-weighted average over the
+    /// bases they could represent (equivalent to `scan_with_policy(seq, AmbiguousBasePolicy::Average)`).
     pub fn scan(&self, seq: &Sequence) -> Vec<Score> {
+        self.scan_with_policy(seq, AmbiguousBasePolicy::Average)
+    }
+
+    /// Scan `seq`, applying `policy` to windows containing IUPAC ambiguity codes, soft-masked
+    /// or otherwise unrecognized bases. A single base outside the IUPAC DNA alphabet only drops
+    /// the windows it falls in, rather than aborting the whole scan.
+    pub fn scan_with_policy(&self, seq: &Sequence, policy: AmbiguousBasePolicy) -> Vec<Score> {
         let (s,e) = match self.strand {
             Strand::Forward => (1, self.length),
             Strand::Reverse => (self.length, 1),
         };
-        let scores: Vec<Score> = seq.bases
+        seq.bases
             .windows(self.length)
-            .map(
-                |w| {
-                    w.iter().enumerate().fold(0.0, |acc, (i, b)| {
-                        acc + self.probs[i][Self::lookup(b)] * self.conservation[i]
-                    })
-                }, 
-            )
             .enumerate()
-            .map(|v: (usize, f64)| Score {seq_start: v.0 + s, seq_end: v.0 + e, algn_start: seq.idxs[v.0 + s - 1] + 1, algn_end: seq.idxs[v.0 + e - 1] + 1, score: v.1 / self.max_score})
+            .filter_map(|(i, w)| {
+                let (raw, ambiguous) = self.score_window(w)?;
+                if let AmbiguousBasePolicy::SkipWindow { max_ambiguous } = policy {
+                    if ambiguous > max_ambiguous {
+                        return None;
+                    }
+                }
+                Some(Score {
+                    seq_start: i + s,
+                    seq_end: i + e,
+                    algn_start: seq.idxs[i + s - 1] + 1,
+                    algn_end: seq.idxs[i + e - 1] + 1,
+                    score: self.normalize(raw),
+                    raw_score: raw,
+                    pvalue: None,
+                    qvalue: None,
+                    strand: self.strand,
+                })
+            })
             .filter(|v| v.score >= self.threshold)
+            .collect()
+    }
+
+    /// Scan `seq`, keeping windows whose raw score reaches the score threshold that
+    /// `threshold_for_pvalue` derives for `pvalue` under `background` (uniform 0.25 per base if
+    /// `None`), instead of the normalized `self.threshold`. The tail probability at that boundary
+    /// is the first one to exceed `pvalue`, so hits at the boundary score can carry a p-value
+    /// above `pvalue`.
+    ///
+    /// `threshold_for_pvalue`/`pvalue_for_score` operate on raw (pre-`normalize`) scores, which
+    /// live on a different scale than the `threshold` field `scan` compares against (e.g. a
+    /// log-odds sum can be negative, while `scan`'s threshold is normalized to `[0, 1]`). This is
+    /// the entry point that actually wires a p-value into scanning: hits are filtered on the raw
+    /// score and annotated with their exact p-value, while `score` still reports the normalized
+    /// `[0, 1]` value for display.
+    pub fn scan_by_pvalue(&self, seq: &Sequence, pvalue: f64, background: Option<&[f64; 4]>) -> Vec<Score> {
+        let uniform = [0.25; 4];
+        let background = background.unwrap_or(&uniform);
+        let raw_threshold = self.threshold_for_pvalue(pvalue, Some(background));
+
+        let (s, e) = match self.strand {
+            Strand::Forward => (1, self.length),
+            Strand::Reverse => (self.length, 1),
+        };
+        seq.bases
+            .windows(self.length)
+            .enumerate()
+            .filter_map(|(i, w)| {
+                let (raw, _) = self.score_window(w)?;
+                if raw < raw_threshold {
+                    return None;
+                }
+                Some(Score {
+                    seq_start: i + s,
+                    seq_end: i + e,
+                    algn_start: seq.idxs[i + s - 1] + 1,
+                    algn_end: seq.idxs[i + e - 1] + 1,
+                    score: self.normalize(raw),
+                    raw_score: raw,
+                    pvalue: Some(self.pvalue_for_score(raw, background)),
+                    qvalue: None,
+                    strand: self.strand,
+                })
+            })
+            .collect()
+    }
+
+    /// Two forward/reverse hits at the same window are only the same palindromic site (rather
+    /// than two distinct binding sites that happen to overlap) when their scores match this
+    /// closely.
+    const PALINDROME_SCORE_TOLERANCE: f64 = 1e-9;
+
+    /// Score both orientations of `seq` in a single pass, deriving the reverse-complement
+    /// orientation from this matrix's own counts instead of requiring a second `DNAMatrix` built
+    /// with `Strand::Reverse`. Call this on a matrix constructed with `Strand::Forward`.
+    ///
+    /// Unlike `scan`, hits always carry canonical `start < end` coordinates plus an explicit
+    /// `strand` field, ready to write out as BED/GFF-style intervals. A window that scores above
+    /// `threshold` on both strands is, in general, two distinct binding sites (the motif on one
+    /// strand, a different site matching its revcomp on the other) and both are reported. Only
+    /// when the two scores match within `PALINDROME_SCORE_TOLERANCE` — i.e. the window is a
+    /// genuine palindrome scoring the same site twice — are they collapsed into one hit, keeping
+    /// the higher-scoring (or, on an exact tie, forward) strand.
+    pub fn scan_both(&self, seq: &Sequence) -> Vec<Score> {
+        seq.bases
+            .windows(self.length)
+            .enumerate()
+            .flat_map(|(i, w)| {
+                let algn_start = seq.idxs[i] + 1;
+                let algn_end = seq.idxs[i + self.length - 1] + 1;
+
+                let forward = self.score_window(w).map(|(raw, _)| (raw, self.normalize(raw)));
+                let rev_comp: Vec<char> = w.iter().rev().map(|b| Self::complement(*b)).collect();
+                let reverse = self.score_window(&rev_comp).map(|(raw, _)| (raw, self.normalize(raw)));
+
+                let make_hit = |(raw, score): (f64, f64), strand| Score {
+                    seq_start: i + 1,
+                    seq_end: i + self.length,
+                    algn_start,
+                    algn_end,
+                    score,
+                    raw_score: raw,
+                    pvalue: None,
+                    qvalue: None,
+                    strand,
+                };
+
+                let forward_hit = forward.filter(|&(_, s)| s >= self.threshold).map(|v| make_hit(v, Strand::Forward));
+                let reverse_hit = reverse.filter(|&(_, s)| s >= self.threshold).map(|v| make_hit(v, Strand::Reverse));
+
+                match (forward_hit, reverse_hit) {
+                    (Some(f), Some(r)) => {
+                        if (f.score - r.score).abs() < Self::PALINDROME_SCORE_TOLERANCE {
+                            vec![if f.score >= r.score { f } else { r }]
+                        } else {
+                            vec![f, r]
+                        }
+                    }
+                    (Some(f), None) => vec![f],
+                    (None, Some(r)) => vec![r],
+                    (None, None) => vec![],
+                }
+            })
+            .collect()
+    }
+
+    /// The complementary base of `b`, preserving case. IUPAC ambiguity codes complement to the
+    /// code covering the complementary set of bases; `N` and unrecognized characters are their
+    /// own complement.
+    fn complement(b: char) -> char {
+        match b {
+            'A' => 'T', 'a' => 't',
+            'T' => 'A', 't' => 'a',
+            'C' => 'G', 'c' => 'g',
+            'G' => 'C', 'g' => 'c',
+            'R' => 'Y', 'r' => 'y',
+            'Y' => 'R', 'y' => 'r',
+            'S' => 'S', 's' => 's',
+            'W' => 'W', 'w' => 'w',
+            'K' => 'M', 'k' => 'm',
+            'M' => 'K', 'm' => 'k',
+            'B' => 'V', 'b' => 'v',
+            'V' => 'B', 'v' => 'b',
+            'D' => 'H', 'd' => 'h',
+            'H' => 'D', 'h' => 'd',
+            other => other,
+        }
+    }
+
+    /// Score one window, returning the raw summed score and the number of ambiguous/unknown
+    /// bases it contained, or `None` if a base is outside the IUPAC DNA alphabet entirely.
+    fn score_window(&self, w: &[char]) -> Option<(f64, usize)> {
+        let mut raw = 0.0;
+        let mut ambiguous = 0;
+        for (i, b) in w.iter().enumerate() {
+            if Self::lookup(b).is_none() {
+                ambiguous += 1;
+            }
+            raw += self.base_score(i, b)?;
+        }
+        Some((raw, ambiguous))
+    }
+
+    /// The contribution of base `b` at column `i`. Canonical A/C/G/T (any case) score directly;
+    /// IUPAC ambiguity codes and `N` score as the background-weighted average over the bases
+    /// they represent. Returns `None` for characters outside the IUPAC DNA alphabet.
+    fn base_score(&self, i: usize, b: &char) -> Option<f64> {
+        if let Some(base) = Self::lookup(b) {
+            return Some(self.column_score(i, base));
+        }
+        let bases = Self::iupac_bases(*b)?;
+        let total_weight: f64 = bases.iter().map(|&base| self.background[base]).sum();
+        Some(
+            bases
+                .iter()
+                .map(|&base| self.column_score(i, base) * self.background[base])
+                .sum::<f64>()
+                / total_weight,
+        )
+    }
+
+    /// Map an IUPAC ambiguity code (or plain base, case-insensitive) to the set of canonical
+    /// bases it represents. Returns `None` for characters outside the IUPAC DNA alphabet.
+    fn iupac_bases(b: char) -> Option<&'static [usize]> {
+        match b.to_ascii_uppercase() {
+            'A' => Some(&[0]),
+            'C' => Some(&[1]),
+            'G' => Some(&[2]),
+            'T' => Some(&[3]),
+            'R' => Some(&[0, 2]),    // puRine: A/G
+            'Y' => Some(&[1, 3]),    // pYrimidine: C/T
+            'S' => Some(&[1, 2]),    // Strong: C/G
+            'W' => Some(&[0, 3]),    // Weak: A/T
+            'K' => Some(&[2, 3]),    // Keto: G/T
+            'M' => Some(&[0, 1]),    // aMino: A/C
+            'B' => Some(&[1, 2, 3]), // not A
+            'D' => Some(&[0, 2, 3]), // not C
+            'H' => Some(&[0, 1, 3]), // not G
+            'V' => Some(&[0, 1, 2]), // not T
+            'N' => Some(&[0, 1, 2, 3]),
+            _ => None,
+        }
+    }
+
+    /// The contribution of `base` at column `i`, under whichever `ScoringModel` this matrix uses.
+    fn column_score(&self, i: usize, base: usize) -> f64 {
+        match self.scoring {
+            ScoringModel::Conservation => self.probs[i][base] * self.conservation[i],
+            ScoringModel::LogOdds { .. } => self.probs[i][base],
+        }
+    }
+
+    /// Map a raw summed score onto `[0, 1]`: raw scores in `[0, max_score]` for
+    /// conservation-weighted scoring, or in `[min_score, max_score]` for log-odds scoring.
+    fn normalize(&self, raw_score: f64) -> f64 {
+        match self.scoring {
+            ScoringModel::Conservation => raw_score / self.max_score,
+            ScoringModel::LogOdds { .. } => (raw_score - self.min_score) / (self.max_score - self.min_score),
+        }
+    }
+
+    /// Discretization factor `G` used by the score-distribution DP: column scores are rounded
+    /// to the nearest `1/G`, bounding the discretization error of a total score at roughly
+    /// `length * 1/G`.
+    const PVALUE_GRANULARITY: f64 = 1e4;
+
+    /// The exact distribution of raw (pre-`normalize`) scores under `background`, computed with
+    /// the standard PWM score-distribution dynamic program (TFMPvalue): each column's score is
+    /// discretized to an integer by multiplying by `Self::PVALUE_GRANULARITY` and rounding, and
+    /// `Q` is built up column by column as `Q'[s + round(G*colscore[b])] += Q[s] * background[b]`.
+    /// The returned map gives P(total score = s) for each reachable discretized score `s`.
+    fn score_distribution(&self, background: &[f64; 4]) -> BTreeMap<i64, f64> {
+        let mut q: BTreeMap<i64, f64> = BTreeMap::new();
+        q.insert(0, 1.0);
+        for i in 0..self.length {
+            let mut next: BTreeMap<i64, f64> = BTreeMap::new();
+            for (&s, &p) in q.iter() {
+                for (base, &bg) in background.iter().enumerate() {
+                    let delta = (Self::PVALUE_GRANULARITY * self.column_score(i, base)).round() as i64;
+                    *next.entry(s + delta).or_insert(0.0) += p * bg;
+                }
+            }
+            q = next;
+        }
+        q
+    }
+
+    /// The p-value of a raw score `T` against an already-computed score `distribution`: the tail
+    /// sum `sum_{s >= round(G*T)} Q[s]`. Factored out of `pvalue_for_score` so callers scoring many
+    /// hits against the same matrix (e.g. `fdr_filter`) can build the distribution once and reuse
+    /// it, instead of re-running the O(length) DP per hit.
+    fn pvalue_at_boundary(distribution: &BTreeMap<i64, f64>, raw_score: f64) -> f64 {
+        let boundary = (Self::PVALUE_GRANULARITY * raw_score).round() as i64;
+        distribution.range(boundary..).map(|(_, p)| p).sum()
+    }
+
+    /// The p-value of a raw score threshold `T` under `background`: the tail sum
+    /// `sum_{s >= round(G*T)} Q[s]` of the score distribution.
+    pub fn pvalue_for_score(&self, raw_score: f64, background: &[f64; 4]) -> f64 {
+        Self::pvalue_at_boundary(&self.score_distribution(background), raw_score)
+    }
+
+    /// Derive the raw score threshold corresponding to a target p-value under `background`
+    /// (uniform 0.25 per base if `None`), by walking the score distribution from the highest
+    /// score downward and accumulating probability until the tail first exceeds `pvalue`.
+    pub fn threshold_for_pvalue(&self, pvalue: f64, background: Option<&[f64; 4]>) -> f64 {
+        let uniform = [0.25; 4];
+        let background = background.unwrap_or(&uniform);
+        let distribution = self.score_distribution(background);
+        let mut cumulative = 0.0;
+        let mut boundary = *distribution.keys().next().unwrap_or(&0);
+        for (&s, &p) in distribution.iter().rev() {
+            cumulative += p;
+            boundary = s;
+            if cumulative > pvalue {
+                break;
+            }
+        }
+        boundary as f64 / Self::PVALUE_GRANULARITY
+    }
+
+    /// Apply Benjamini-Hochberg FDR control across `hits`, which may be pooled from several
+    /// matrices scanned together (each hit travels with the matrix it came from, so its p-value
+    /// is computed against the right score distribution). Each distinct matrix's score
+    /// distribution is computed once and reused across all of its hits, rather than re-running
+    /// the O(length) DP per hit. P-values are sorted ascending and the largest rank `k` of `m`
+    /// tests satisfying `p_(k) <= (k/m)*alpha` is found; hits beyond that rank are dropped.
+    /// Survivors are annotated with the standard step-up q-value `q_(k) = min(q_(k+1), p_(k) * m
+    /// / k)`, walked from the largest rank down so q-values are monotone non-decreasing with p.
+    pub fn fdr_filter(hits: Vec<(Score, &DNAMatrix)>, background: &[f64; 4], alpha: f64) -> Vec<Score> {
+        let mut distributions: HashMap<*const DNAMatrix, BTreeMap<i64, f64>> = HashMap::new();
+        let mut with_pvalue: Vec<(Score, f64)> = hits
+            .into_iter()
+            .map(|(mut hit, matrix)| {
+                let distribution = distributions
+                    .entry(matrix as *const DNAMatrix)
+                    .or_insert_with(|| matrix.score_distribution(background));
+                let p = Self::pvalue_at_boundary(distribution, hit.raw_score);
+                hit.pvalue = Some(p);
+                (hit, p)
+            })
             .collect();
-        scores
+        with_pvalue.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let m = with_pvalue.len();
+        let mut max_rank = 0;
+        for (k, &(_, p)) in with_pvalue.iter().enumerate() {
+            let rank = k + 1;
+            if p <= (rank as f64 / m as f64) * alpha {
+                max_rank = rank;
+            }
+        }
+        with_pvalue.truncate(max_rank);
+
+        let mut running_min = 1.0_f64;
+        let mut qvalues = vec![0.0; with_pvalue.len()];
+        for (k, &(_, p)) in with_pvalue.iter().enumerate().rev() {
+            let rank = k + 1;
+            running_min = running_min.min((p * m as f64 / rank as f64).min(1.0));
+            qvalues[k] = running_min;
+        }
+
+        with_pvalue
+            .into_iter()
+            .zip(qvalues)
+            .map(|((mut hit, _), q)| {
+                hit.qvalue = Some(q);
+                hit
+            })
+            .collect()
     }
 
-    fn lookup(b: &char) -> usize {
+    /// Index a plain base (any case) into `probs`/`background`. Returns `None` for anything
+    /// else (IUPAC ambiguity codes, `N`, gaps); when scoring windows, `base_score` resolves
+    /// those via `iupac_bases`.
+    fn lookup(b: &char) -> Option<usize> {
         match b {
-            'A' | 'a' => 0,
-            'C' | 'c' => 1,
-            'G' | 'g' => 2,
-            'T' | 't' => 3,
-            _ => panic!("DNA base unknown {}", b),
+            'A' | 'a' => Some(0),
+            'C' | 'c' => Some(1),
+            'G' | 'g' => Some(2),
+            'T' | 't' => Some(3),
+            _ => None,
         }
     }
 }
@@ -222,14 +779,14 @@ mod tests {
 
     #[test]
     fn test_scan_forward() {
-        let c = &vec![
+        let c = &[
             vec![2.0, 0.0, 0.0, 0.0],
             vec![1.0, 0.0, 0.0, 1.0],
             vec![1.0, 0.0, 0.0, 0.0],
             vec![0.50, 0.50, 0.50, 0.50],
         ];
         let s = Strand::Forward;
-        let m = super::DNAMatrix::new("teste", 0.5, c, s);
+        let m = super::DNAMatrix::new("teste", 0.5, c, s, ScoringModel::Conservation);
         let seq = Sequence::from("-ACG-TACGTACGTAGATGTCTAGTACGTACGCTAGCTAGCTGAGACTGACTAGTACGTAAGCTAGCACG");
         let scores = m.scan(&seq);
         println!("{:?}", scores);
@@ -237,19 +794,205 @@ mod tests {
 
     #[test]
     fn test_scan_reverse() {
-        let c = &vec![
+        let c = &[
             vec![2.0, 0.0, 0.0, 0.0],
             vec![1.0, 0.0, 0.0, 1.0],
             vec![1.0, 0.0, 0.0, 0.0],
             vec![0.50, 0.50, 0.50, 0.50],
         ];
         let s = Strand::Reverse;
-        let m = super::DNAMatrix::new("teste", 0.5, c, s);
+        let m = super::DNAMatrix::new("teste", 0.5, c, s, ScoringModel::Conservation);
         let seq = Sequence::from("-ACG-TACGTACGTAGATGTCTAGTACGTACGCTAGCTAGCTGAGACTGACTAGTACGTAAGCTAGCACG");
         let scores = m.scan(&seq);
         println!("{:?}", scores);
     }
 
+    #[test]
+    fn test_log_odds_known_value() {
+        let counts = vec![vec![4.0, 0.0, 0.0, 0.0]];
+        let background = [0.25; 4];
+        let probs = super::DNAMatrix::calculate_log_odds(&counts, 0.25, &background);
+        // freq_A = (4 + 0.25) / (4 + 4*0.25) = 0.85; log2(0.85 / 0.25)
+        assert!((probs[0][0] - (0.85_f64 / 0.25).log2()).abs() < 1e-9);
+        // freq_C = (0 + 0.25) / 5 = 0.05; log2(0.05 / 0.25)
+        assert!((probs[0][1] - (0.05_f64 / 0.25).log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pvalue_threshold_round_trip() {
+        // A single-column matrix strongly favoring A: only the "A" outcome (p=0.25) reaches the
+        // top score tier, the other three bases share the next tier (p=0.75 combined).
+        let counts = vec![vec![4.0, 0.0, 0.0, 0.0]];
+        let background = [0.25; 4];
+        let m = super::DNAMatrix::new("test", 0.0, &counts, Strand::Forward, ScoringModel::log_odds());
+
+        let col_a = (0.85_f64 / 0.25).log2();
+        let col_other = (0.05_f64 / 0.25).log2();
+
+        let p_a = m.pvalue_for_score(col_a, &background);
+        assert!((p_a - 0.25).abs() < 1e-9);
+
+        // A target p-value inside (0, 0.25) is only cleared by the "A" tier. Tolerance accounts
+        // for the DP's discretization error (~1/(2*PVALUE_GRANULARITY)).
+        let threshold = m.threshold_for_pvalue(0.2, Some(&background));
+        assert!((threshold - col_a).abs() < 1e-4);
+
+        // A looser target that the "A" tier alone can't satisfy drops the boundary a tier.
+        let threshold_loose = m.threshold_for_pvalue(0.26, Some(&background));
+        assert!((threshold_loose - col_other).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_scan_both_dedups_palindromic_hit() {
+        // "AT" is its own reverse complement, so a matrix favoring "AT" scores identically on
+        // both strands at the same window; scan_both must report it once (forward, on the tie).
+        let counts = vec![vec![4.0, 0.0, 0.0, 0.0], vec![0.0, 0.0, 0.0, 4.0]];
+        let m = super::DNAMatrix::new("test", 0.0, &counts, Strand::Forward, ScoringModel::Conservation);
+        let seq = Sequence::from("AT");
+
+        let hits = m.scan_both(&seq);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].seq_start, 1);
+        assert_eq!(hits[0].seq_end, 2);
+        assert_eq!(hits[0].strand, Strand::Forward);
+    }
+
+    #[test]
+    fn test_scan_both_keeps_distinct_hits_on_non_palindromic_windows() {
+        // "ACG" is not its own reverse complement ("CGT"), so forward and reverse scores at a
+        // given window generally differ: both strands represent distinct binding sites and both
+        // must be kept, not collapsed down to one hit per window. Log-odds scoring with noisy
+        // counts gives every column a distinct, non-round score so mismatched windows don't
+        // accidentally tie (unlike an all-or-nothing conservation score, where every non-match
+        // scores exactly 0 and would be misread as a coincidental "palindrome").
+        let counts = vec![
+            vec![30.0, 1.0, 1.0, 1.0],
+            vec![1.0, 12.0, 1.0, 1.0],
+            vec![1.0, 1.0, 4.0, 1.0],
+        ];
+        let m = super::DNAMatrix::new("test", f64::MIN, &counts, Strand::Forward, ScoringModel::log_odds());
+        let seq = Sequence::from("ACGATC");
+
+        // 6 bases, a 3-column matrix: 4 window positions, each scoring on both strands.
+        let hits = m.scan_both(&seq);
+        assert_eq!(hits.len(), 8);
+        assert_eq!(hits.iter().filter(|h| h.strand == Strand::Forward).count(), 4);
+        assert_eq!(hits.iter().filter(|h| h.strand == Strand::Reverse).count(), 4);
+    }
+
+    #[test]
+    fn test_fdr_filter_qvalue_uses_total_tests() {
+        let counts = vec![vec![4.0, 0.0, 0.0, 0.0]];
+        let m = super::DNAMatrix::new("test", 0.0, &counts, Strand::Forward, ScoringModel::log_odds());
+        let background = [0.25; 4];
+
+        let col_a = (0.85_f64 / 0.25).log2();
+        let col_other = (0.05_f64 / 0.25).log2();
+
+        let make_hit = |raw_score: f64| Score {
+            seq_start: 1,
+            seq_end: 1,
+            algn_start: 1,
+            algn_end: 1,
+            score: 0.0,
+            raw_score,
+            pvalue: None,
+            qvalue: None,
+            strand: Strand::Forward,
+        };
+
+        // Five pooled hits: two with p=0.25 (the "A" tier), three with p=1.0 (everything else).
+        let hits = vec![
+            (make_hit(col_a), &m),
+            (make_hit(col_a), &m),
+            (make_hit(col_other), &m),
+            (make_hit(col_other), &m),
+            (make_hit(col_other), &m),
+        ];
+
+        let survivors = super::DNAMatrix::fdr_filter(hits, &background, 0.9);
+
+        // Only the two p=0.25 hits clear BH at alpha=0.9 out of m=5 total tests.
+        assert_eq!(survivors.len(), 2);
+        for hit in &survivors {
+            // q = p * m / rank, stepped up: using m=5 (total tests) both hits land at 0.625.
+            // The old (buggy) kept-based formula (m replaced by kept=2) would give 0.5 and 0.25.
+            assert!((hit.qvalue.unwrap() - 0.625).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_ambiguous_base_scores_as_background_average() {
+        let counts = vec![vec![4.0, 0.0, 0.0, 0.0]];
+        let m = super::DNAMatrix::new("test", 0.0, &counts, Strand::Forward, ScoringModel::log_odds());
+
+        let col_a = (0.85_f64 / 0.25).log2();
+        let col_other = (0.05_f64 / 0.25).log2();
+        let expected_n = (col_a + 3.0 * col_other) / 4.0;
+
+        assert!((m.base_score(0, &'N').unwrap() - expected_n).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_seqs_matches_manual_counts() {
+        let m = super::DNAMatrix::from_seqs(
+            "test",
+            &["AC", "AG", "AT"],
+            0.0,
+            Strand::Forward,
+            ScoringModel::Conservation,
+        );
+        assert_eq!(m.length, 2);
+        // Column 0 is all A; column 1 has one each of C/G/T.
+        assert_eq!(m.probs[0][0], 1.0);
+        assert!((m.probs[1][1] - 1.0 / 3.0).abs() < 1e-9);
+        assert!((m.probs[1][2] - 1.0 / 3.0).abs() < 1e-9);
+        assert!((m.probs[1][3] - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one aligned sequence is required")]
+    fn test_from_seqs_rejects_empty_input() {
+        super::DNAMatrix::from_seqs("test", &[], 0.0, Strand::Forward, ScoringModel::Conservation);
+    }
+
+    #[test]
+    fn test_parse_jaspar_bracket_format() {
+        let contents = ">MA0001.1 AGL3\nA  [ 4 19  0  0  0 ]\nC  [16  0 20  0  0 ]\nG  [ 0  1  0  0  0 ]\nT  [ 0  0  0 20 20 ]\n";
+        let (name, counts) = super::DNAMatrix::parse_jaspar(contents).unwrap();
+        assert_eq!(name, "AGL3");
+        assert_eq!(counts, vec![
+            vec![4.0, 16.0, 0.0, 0.0],
+            vec![19.0, 0.0, 1.0, 0.0],
+            vec![0.0, 20.0, 0.0, 0.0],
+            vec![0.0, 0.0, 0.0, 20.0],
+            vec![0.0, 0.0, 0.0, 20.0],
+        ]);
+    }
+
+    #[test]
+    fn test_parse_jaspar_raw_pfm_format() {
+        // No header, no brackets, no base letters: rows are assumed A/C/G/T in order.
+        let contents = " 4 19  0  0  0\n16  0 20  0  0\n 0  1  0  0  0\n 0  0  0 20 20\n";
+        let (name, counts) = super::DNAMatrix::parse_jaspar(contents).unwrap();
+        assert_eq!(name, "unknown");
+        assert_eq!(counts, vec![
+            vec![4.0, 16.0, 0.0, 0.0],
+            vec![19.0, 0.0, 1.0, 0.0],
+            vec![0.0, 20.0, 0.0, 0.0],
+            vec![0.0, 0.0, 0.0, 20.0],
+            vec![0.0, 0.0, 0.0, 20.0],
+        ]);
+    }
+
+    #[test]
+    fn test_parse_transfac() {
+        let contents = "ID test_motif\nP0 A C G T\n01 4 16 0 0\n02 19 0 1 0\nXX\n//\n";
+        let (name, counts) = super::DNAMatrix::parse_transfac(contents).unwrap();
+        assert_eq!(name, "test_motif");
+        assert_eq!(counts, vec![vec![4.0, 16.0, 0.0, 0.0], vec![19.0, 0.0, 1.0, 0.0]]);
+    }
+
     // #[test]
     // fn create_dna_matrix() {
     //     let v = vec![vec![837.0, 1889.0, 1280.0, 718.0], vec![193.0, 0.0, 0.0, 4725.0], vec![4725.0, 65.0, 275.0, 1232.0]];